@@ -1,9 +1,10 @@
 use crate::{
     error::AppResult,
-    state::{DesiredState, StateEvent},
+    state::{DesiredState, StateEvent, temp_path_for},
 };
 use log::{debug, info, warn};
 use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
 use std::env;
 use std::fs;
 use std::io::{self, ErrorKind};
@@ -13,23 +14,39 @@ use std::time::Duration;
 
 const EVENT_LOOP_TICK: Duration = Duration::from_secs(1);
 
+/// Output style for the standalone watcher's log lines, selected with
+/// `--format`. `Json` emits one newline-delimited JSON object per line so
+/// the watcher's output can be piped into log processors and other
+/// tooling; `Text` (the default) keeps the human-oriented lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
 pub fn run() -> AppResult<()> {
-    let state_path = parse_args(env::args().skip(1).collect())?;
+    let (state_path, format) = parse_args(env::args().skip(1).collect())?;
     let mut state = DesiredState::load(state_path.clone())?;
-    let state_events = state.subscribe();
+    let state_events = state.subscribe_with_current_state();
     let watch_target = canonicalize_for_watch(&state_path);
+    let watch_dir = watch_directory(&watch_target);
 
     let (watch_tx, watch_rx) = mpsc::channel();
     let mut watcher = notify::recommended_watcher(move |res| {
         let _ = watch_tx.send(res);
     })?;
 
+    // Watch the parent directory rather than the file itself: `persist()`
+    // replaces the file via `rename`, which on Linux severs inotify's watch
+    // on the old inode (IN_DELETE_SELF/IN_IGNORED) after the very first
+    // write, silently killing any watch placed directly on the path.
+    // Watching the directory and filtering by filename survives renames.
     watcher
-        .watch(&watch_target, RecursiveMode::NonRecursive)
+        .watch(&watch_dir, RecursiveMode::NonRecursive)
         .map_err(|err| {
             io::Error::new(
                 ErrorKind::Other,
-                format!("failed to watch {}: {err}", watch_target.display()),
+                format!("failed to watch {}: {err}", watch_dir.display()),
             )
         })?;
 
@@ -37,13 +54,12 @@ pub fn run() -> AppResult<()> {
         "Desired state watcher running. Monitoring '{}'",
         watch_target.display()
     );
-    println!("Desired state watcher running. Press Ctrl+C to stop.");
+    emit_lifecycle_message(format, "Desired state watcher running. Press Ctrl+C to stop.");
 
-    state.emit_current_state();
-    drain_state_events(&state_events);
+    drain_state_events(&state_events, format);
 
     loop {
-        drain_state_events(&state_events);
+        drain_state_events(&state_events, format);
 
         match watch_rx.recv_timeout(EVENT_LOOP_TICK) {
             Ok(Ok(event)) => {
@@ -55,20 +71,23 @@ pub fn run() -> AppResult<()> {
                 if event_affects_target(&event, &watch_target) && is_state_change(&event.kind) {
                     if let Err(err) = state.reload_from_disk() {
                         warn!("Failed to reload desired state: '{}'", err);
+                        emit_error(format, &format!("failed to reload desired state: {err}"));
                     } else {
                         info!("Reloaded desired state after file change");
-                        drain_state_events(&state_events);
+                        drain_state_events(&state_events, format);
                     }
                 }
             }
             Ok(Err(err)) => {
                 warn!("File watch error: '{}'", err);
+                emit_error(format, &format!("file watch error: {err}"));
             }
             Err(RecvTimeoutError::Timeout) => {
                 // no-op, loop again to keep draining events
             }
             Err(RecvTimeoutError::Disconnected) => {
                 warn!("File watcher disconnected unexpectedly");
+                emit_error(format, "file watcher disconnected unexpectedly");
                 return Err(io::Error::new(
                     ErrorKind::Other,
                     "file watcher disconnected unexpectedly",
@@ -79,9 +98,10 @@ pub fn run() -> AppResult<()> {
     }
 }
 
-fn parse_args(args: Vec<String>) -> AppResult<PathBuf> {
+fn parse_args(args: Vec<String>) -> AppResult<(PathBuf, OutputFormat)> {
     let mut desired_file =
         env::var("DESIRED_STATE_FILE").unwrap_or_else(|_| "desired_state.yml".to_string());
+    let mut format = OutputFormat::Text;
 
     let mut idx = 0;
     while idx < args.len() {
@@ -93,28 +113,63 @@ fn parse_args(args: Vec<String>) -> AppResult<PathBuf> {
                 desired_file = value.clone();
                 idx += 2;
             }
+            "--format" => {
+                let value = args
+                    .get(idx + 1)
+                    .ok_or_else(|| invalid_argument("--format requires 'text' or 'json'"))?;
+                format = parse_format(value)?;
+                idx += 2;
+            }
+            "--watch-only" => {
+                // Consumed by `main` to choose this CLI entry point in the
+                // first place; `run` also sees it here because it re-reads
+                // `env::args()` rather than taking a filtered list.
+                idx += 1;
+            }
             other => {
                 return Err(invalid_argument(format!("unknown argument: {other}")).into());
             }
         }
     }
 
-    Ok(PathBuf::from(desired_file))
+    Ok((PathBuf::from(desired_file), format))
+}
+
+fn parse_format(value: &str) -> AppResult<OutputFormat> {
+    match value {
+        "text" => Ok(OutputFormat::Text),
+        "json" => Ok(OutputFormat::Json),
+        other => Err(invalid_argument(format!(
+            "invalid --format value '{other}', expected 'text' or 'json'"
+        ))
+        .into()),
+    }
 }
 
 fn canonicalize_for_watch(path: &Path) -> PathBuf {
     fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
 }
 
+fn watch_directory(target: &Path) -> PathBuf {
+    target
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf()
+}
+
 fn event_affects_target(event: &Event, target: &Path) -> bool {
     if event.paths.is_empty() {
         return true;
     }
 
-    event
-        .paths
-        .iter()
-        .any(|path| canonicalize_for_watch(path) == target)
+    let temp_target = temp_path_for(target);
+    event.paths.iter().any(|path| {
+        if path == &temp_target {
+            return false;
+        }
+        canonicalize_for_watch(path) == target
+    })
 }
 
 fn is_state_change(kind: &EventKind) -> bool {
@@ -124,13 +179,14 @@ fn is_state_change(kind: &EventKind) -> bool {
     )
 }
 
-fn drain_state_events(receiver: &mpsc::Receiver<StateEvent>) {
+fn drain_state_events(receiver: &mpsc::Receiver<StateEvent>, format: OutputFormat) {
     loop {
         match receiver.try_recv() {
-            Ok(event) => log_state_event(&event),
+            Ok(event) => log_state_event(&event, format),
             Err(TryRecvError::Empty) => break,
             Err(TryRecvError::Disconnected) => {
                 warn!("State event channel disconnected; stopping log loop.");
+                emit_error(format, "state event channel disconnected; stopping log loop");
                 break;
             }
         }
@@ -141,17 +197,182 @@ fn invalid_argument(msg: impl Into<String>) -> io::Error {
     io::Error::new(ErrorKind::InvalidInput, msg.into())
 }
 
-fn log_state_event(event: &StateEvent) {
+fn log_state_event(event: &StateEvent, format: OutputFormat) {
     match event {
-        StateEvent::StateUpdated { version, services } => {
-            println!(
-                "[state-event] file version {} with {} service(s)",
-                version,
-                services.len()
-            );
-            for svc in services {
-                println!("    - {} {}", svc.name, svc.version_req);
+        StateEvent::StateUpdated { version, services } => match format {
+            OutputFormat::Text => {
+                println!(
+                    "[state-event] file version {} with {} service(s)",
+                    version,
+                    services.len()
+                );
+                for svc in services {
+                    println!("    - {} {}", svc.name, svc.version_req);
+                }
             }
-        }
+            OutputFormat::Json => emit_json_line(&StateUpdatedRecord {
+                r#type: "state_updated",
+                version: version.to_string(),
+                services: services
+                    .iter()
+                    .map(|svc| ServiceRecord {
+                        name: svc.name.clone(),
+                        version_req: svc.version_req.to_string(),
+                    })
+                    .collect(),
+            }),
+        },
+    }
+}
+
+#[derive(Serialize)]
+struct ServiceRecord {
+    name: String,
+    version_req: String,
+}
+
+#[derive(Serialize)]
+struct StateUpdatedRecord {
+    r#type: &'static str,
+    version: String,
+    services: Vec<ServiceRecord>,
+}
+
+#[derive(Serialize)]
+struct LifecycleRecord<'a> {
+    r#type: &'static str,
+    message: &'a str,
+}
+
+#[derive(Serialize)]
+struct ErrorRecord<'a> {
+    r#type: &'static str,
+    message: &'a str,
+}
+
+fn emit_lifecycle_message(format: OutputFormat, message: &str) {
+    match format {
+        OutputFormat::Text => println!("{message}"),
+        OutputFormat::Json => emit_json_line(&LifecycleRecord {
+            r#type: "lifecycle",
+            message,
+        }),
+    }
+}
+
+fn emit_error(format: OutputFormat, message: &str) {
+    if format == OutputFormat::Json {
+        emit_json_line(&ErrorRecord {
+            r#type: "error",
+            message,
+        });
+    }
+}
+
+fn emit_json_line(value: &impl Serialize) {
+    match serde_json::to_string(value) {
+        Ok(line) => println!("{line}"),
+        Err(err) => warn!("failed to serialize JSON record: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_args_defaults_to_text_format() {
+        let (path, format) = parse_args(vec!["--file".to_string(), "foo.yml".to_string()]).unwrap();
+        assert_eq!(path, PathBuf::from("foo.yml"));
+        assert_eq!(format, OutputFormat::Text);
+    }
+
+    #[test]
+    fn parse_args_parses_format_json() {
+        let (_, format) = parse_args(vec![
+            "--file".to_string(),
+            "foo.yml".to_string(),
+            "--format".to_string(),
+            "json".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(format, OutputFormat::Json);
+    }
+
+    #[test]
+    fn parse_args_rejects_an_invalid_format_value() {
+        let result = parse_args(vec!["--format".to_string(), "xml".to_string()]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_args_treats_watch_only_as_a_no_op() {
+        let (path, format) = parse_args(vec![
+            "--watch-only".to_string(),
+            "--file".to_string(),
+            "foo.yml".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(path, PathBuf::from("foo.yml"));
+        assert_eq!(format, OutputFormat::Text);
+    }
+
+    #[test]
+    fn parse_args_rejects_unknown_arguments() {
+        let result = parse_args(vec!["--bogus".to_string()]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_format_accepts_text_and_json() {
+        assert_eq!(parse_format("text").unwrap(), OutputFormat::Text);
+        assert_eq!(parse_format("json").unwrap(), OutputFormat::Json);
+    }
+
+    #[test]
+    fn parse_format_rejects_anything_else() {
+        assert!(parse_format("yaml").is_err());
+    }
+
+    #[test]
+    fn state_updated_record_serializes_with_a_type_tag() {
+        let record = StateUpdatedRecord {
+            r#type: "state_updated",
+            version: "0.1.0".to_string(),
+            services: vec![ServiceRecord {
+                name: "api".to_string(),
+                version_req: "^1.2.3".to_string(),
+            }],
+        };
+
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value["type"], "state_updated");
+        assert_eq!(value["version"], "0.1.0");
+        assert_eq!(value["services"][0]["name"], "api");
+        assert_eq!(value["services"][0]["version_req"], "^1.2.3");
+    }
+
+    #[test]
+    fn lifecycle_record_serializes_with_a_type_tag() {
+        let record = LifecycleRecord {
+            r#type: "lifecycle",
+            message: "watcher running",
+        };
+
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value["type"], "lifecycle");
+        assert_eq!(value["message"], "watcher running");
+    }
+
+    #[test]
+    fn error_record_serializes_with_a_type_tag() {
+        let record = ErrorRecord {
+            r#type: "error",
+            message: "something went wrong",
+        };
+
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value["type"], "error");
+        assert_eq!(value["message"], "something went wrong");
     }
 }
@@ -1,9 +1,14 @@
-use crate::{error::AppResult, state::Service};
+use crate::{
+    error::AppResult,
+    state::{Service, temp_path_for},
+    status::ProbeSpec,
+};
 use log::{debug, info, warn};
 use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fs;
+use std::io;
 use std::path::Path;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +23,8 @@ struct DesiredStateFile {
 struct DesiredStateFileService {
     name: String,
     version: VersionReq,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    probe: Option<ProbeSpec>,
 }
 
 impl From<DesiredStateFileService> for Service {
@@ -25,6 +32,7 @@ impl From<DesiredStateFileService> for Service {
         Service {
             name: record.name,
             version_req: record.version,
+            probe: record.probe,
         }
     }
 }
@@ -34,6 +42,7 @@ impl From<&Service> for DesiredStateFileService {
         DesiredStateFileService {
             name: service.name.clone(),
             version: service.version_req.clone(),
+            probe: service.probe.clone(),
         }
     }
 }
@@ -70,8 +79,8 @@ pub fn read(path: &Path) -> AppResult<(Version, BTreeMap<String, Service>)> {
         return Ok((current_file_version(), BTreeMap::new()));
     }
 
-    let parsed: DesiredStateFile = match serde_yaml::from_str(&yaml_string) {
-        Ok(parsed) => parsed,
+    let raw_value: serde_yaml::Value = match serde_yaml::from_str(&yaml_string) {
+        Ok(value) => value,
         Err(err) => {
             warn!(
                 "Failed to parse desired state file '{}'. Treating as empty. Err: {}",
@@ -82,6 +91,46 @@ pub fn read(path: &Path) -> AppResult<(Version, BTreeMap<String, Service>)> {
         }
     };
 
+    let file_version = extract_version(&raw_value).unwrap_or_else(current_file_version);
+
+    if is_unsupported(&file_version, &current_file_version()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "desired state file '{}' has schema version '{}', which is newer than the supported '{}'; refusing to load it",
+                path.display(),
+                file_version,
+                current_file_version()
+            ),
+        )
+        .into());
+    }
+
+    let value = if file_version < current_file_version() {
+        info!(
+            "Migrating desired state file '{}' from schema version '{}' to '{}'",
+            path.display(),
+            file_version,
+            current_file_version()
+        );
+        let migrated = migrate(raw_value, &file_version)?;
+        write_raw(path, &migrated)?;
+        migrated
+    } else {
+        raw_value
+    };
+
+    let parsed: DesiredStateFile = serde_yaml::from_value(value).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "failed to parse desired state file {}: {}",
+                path.display(),
+                err
+            ),
+        )
+    })?;
+
     debug!(
         "Loaded desired state version '{}' with {} service(s) from '{}'",
         parsed.version,
@@ -119,7 +168,9 @@ pub fn write(
             .collect(),
     })?;
 
-    fs::write(path, yaml)?;
+    let temp_path = temp_path_for(path);
+    fs::write(&temp_path, yaml)?;
+    fs::rename(&temp_path, path)?;
 
     info!(
         "Persisted desired state with {} service(s) to '{}'",
@@ -129,19 +180,60 @@ pub fn write(
     Ok(())
 }
 
-pub fn ensure_exists(path: &Path) -> AppResult<()> {
-    if path.exists() {
-        return Ok(());
+fn current_file_version() -> Version {
+    Version::new(0, 1, 0)
+}
+
+// Pre-1.0 semver convention: while major is still 0, a minor bump is
+// treated as breaking; once major reaches 1, only a major bump is.
+fn is_unsupported(file_version: &Version, current: &Version) -> bool {
+    if file_version.major != current.major {
+        return file_version.major > current.major;
     }
-    info!(
-        "Desired state file '{}' does not exist; creating with defaults",
-        path.display()
-    );
-    create_template_file(path)
+    if file_version.major == 0 {
+        return file_version.minor > current.minor;
+    }
+    false
 }
 
-fn current_file_version() -> Version {
-    Version::new(0, 1, 0)
+// Keyed by the version it upgrades *from*; entries must stay sorted
+// ascending by version, since `migrate` relies on that order.
+type Migration = fn(serde_yaml::Value) -> AppResult<serde_yaml::Value>;
+
+const MIGRATIONS: &[(Version, Migration)] = &[];
+
+fn extract_version(value: &serde_yaml::Value) -> Option<Version> {
+    value
+        .get("version")
+        .and_then(|version| version.as_str())
+        .and_then(|version| Version::parse(version).ok())
+}
+
+fn migrate(mut value: serde_yaml::Value, from: &Version) -> AppResult<serde_yaml::Value> {
+    for (migrates_from, migration) in MIGRATIONS {
+        if migrates_from >= from {
+            value = migration(value)?;
+        }
+    }
+    set_version(&mut value, &current_file_version());
+    Ok(value)
+}
+
+fn set_version(value: &mut serde_yaml::Value, version: &Version) {
+    if let serde_yaml::Value::Mapping(mapping) = value {
+        mapping.insert(
+            serde_yaml::Value::String("version".to_string()),
+            serde_yaml::Value::String(version.to_string()),
+        );
+    }
+}
+
+fn write_raw(path: &Path, value: &serde_yaml::Value) -> AppResult<()> {
+    let yaml = serde_yaml::to_string(value)?;
+    let temp_path = temp_path_for(path);
+    fs::write(&temp_path, yaml)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
 }
 
 fn create_template_file(path: &Path) -> AppResult<()> {
@@ -156,11 +248,13 @@ fn create_template_file(path: &Path) -> AppResult<()> {
                 name: "example-service".to_string(),
                 version: VersionReq::parse("^1.2.3")
                     .expect("static version requirement must be valid"),
+                probe: None,
             },
             DesiredStateFileService {
                 name: "second-example-service".to_string(),
                 version: VersionReq::parse(">0.1.0")
                     .expect("static version requirement must be valid"),
+                probe: None,
             },
         ],
     };
@@ -178,3 +272,103 @@ fn create_template_file(path: &Path) -> AppResult<()> {
     info!("Created desired state template at '{}'", path.display());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_version_reads_the_version_field() {
+        let value: serde_yaml::Value =
+            serde_yaml::from_str("version: 0.1.0\nservices: []").unwrap();
+        assert_eq!(extract_version(&value), Some(Version::new(0, 1, 0)));
+    }
+
+    #[test]
+    fn extract_version_is_none_when_missing_or_unparsable() {
+        let missing: serde_yaml::Value = serde_yaml::from_str("services: []").unwrap();
+        assert_eq!(extract_version(&missing), None);
+
+        let bogus: serde_yaml::Value = serde_yaml::from_str("version: not-a-version").unwrap();
+        assert_eq!(extract_version(&bogus), None);
+    }
+
+    #[test]
+    fn migrate_stamps_the_current_version_with_no_migrations_registered() {
+        let value: serde_yaml::Value =
+            serde_yaml::from_str("version: 0.0.1\nservices: []").unwrap();
+        let migrated = migrate(value, &Version::new(0, 0, 1)).unwrap();
+        assert_eq!(extract_version(&migrated), Some(current_file_version()));
+    }
+
+    fn temp_state_path(name: &str) -> std::path::PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "desired_state_file_test_{}_{}.yml",
+            std::process::id(),
+            name
+        ));
+        path
+    }
+
+    #[test]
+    fn read_refuses_a_file_whose_major_version_is_newer() {
+        let path = temp_state_path("newer_major");
+        fs::write(&path, "version: 1.0.0\nservices: []\n").unwrap();
+
+        let result = read(&path);
+
+        assert!(result.is_err());
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn read_refuses_a_file_whose_minor_version_is_newer_while_still_pre_1_0() {
+        let path = temp_state_path("newer_minor_pre_1_0");
+        fs::write(&path, "version: 0.2.0\nservices: []\n").unwrap();
+
+        let result = read(&path);
+
+        assert!(result.is_err());
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn is_unsupported_treats_a_pre_1_0_minor_bump_as_breaking() {
+        let current = Version::new(0, 1, 0);
+        assert!(is_unsupported(&Version::new(0, 2, 0), &current));
+        assert!(!is_unsupported(&Version::new(0, 1, 0), &current));
+        assert!(!is_unsupported(&Version::new(0, 0, 1), &current));
+    }
+
+    #[test]
+    fn is_unsupported_only_treats_a_major_bump_as_breaking_at_1_0_and_above() {
+        let current = Version::new(1, 2, 0);
+        assert!(is_unsupported(&Version::new(2, 0, 0), &current));
+        assert!(!is_unsupported(&Version::new(1, 3, 0), &current));
+    }
+
+    #[test]
+    fn read_migrates_an_older_file_and_rewrites_it_at_the_current_version() {
+        let path = temp_state_path("older_version");
+        fs::write(
+            &path,
+            "version: 0.0.1\nservices:\n  - name: api\n    version: \"^1.2.3\"\n",
+        )
+        .unwrap();
+
+        let (version, services) = read(&path).unwrap();
+
+        assert_eq!(version, current_file_version());
+        assert!(services.contains_key("api"));
+
+        let rewritten = fs::read_to_string(&path).unwrap();
+        let rewritten_value: serde_yaml::Value = serde_yaml::from_str(&rewritten).unwrap();
+        assert_eq!(
+            extract_version(&rewritten_value),
+            Some(current_file_version())
+        );
+
+        fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{
+    error::AppResult,
+    state::{DesiredState, SharedState, StateEvent},
+};
+use log::{info, warn};
+use semver::VersionReq;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::thread;
+
+// One line of JSON per request, mirroring the operations web_api.rs
+// exposes over HTTP.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum GatewayCommand {
+    List,
+    Set { name: String, version: String },
+    Remove { name: String },
+    Subscribe,
+}
+
+#[derive(Debug, Serialize)]
+struct ServiceRecord {
+    name: String,
+    version: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum GatewayResponse {
+    Services { services: Vec<ServiceRecord> },
+    Removed { removed: bool },
+    Event { event: StateEvent },
+    Error { message: String },
+}
+
+pub fn spawn(state: SharedState, socket_path: PathBuf) -> AppResult<()> {
+    if socket_path.exists() {
+        fs::remove_file(&socket_path)?;
+    }
+    if let Some(parent) = socket_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let listener = UnixListener::bind(&socket_path)?;
+    info!(
+        "Gateway listening on unix socket '{}'",
+        socket_path.display()
+    );
+
+    thread::Builder::new()
+        .name("desired-state-gateway".into())
+        .spawn(move || accept_loop(listener, state))?;
+
+    Ok(())
+}
+
+fn accept_loop(listener: UnixListener, state: SharedState) {
+    for incoming in listener.incoming() {
+        match incoming {
+            Ok(stream) => {
+                let state = state.clone();
+                thread::spawn(move || handle_connection(stream, state));
+            }
+            Err(err) => warn!("Gateway accept error: '{}'", err),
+        }
+    }
+}
+
+fn handle_connection(stream: UnixStream, state: SharedState) {
+    let reader = match stream.try_clone() {
+        Ok(stream) => BufReader::new(stream),
+        Err(err) => {
+            warn!("Gateway failed to clone client stream: '{}'", err);
+            return;
+        }
+    };
+    let mut writer = stream;
+
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                warn!("Gateway connection read error: '{}'", err);
+                break;
+            }
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let command: GatewayCommand = match serde_json::from_str(&line) {
+            Ok(command) => command,
+            Err(err) => {
+                let _ = write_response(
+                    &mut writer,
+                    &GatewayResponse::Error {
+                        message: format!("invalid request: {err}"),
+                    },
+                );
+                continue;
+            }
+        };
+
+        // `Subscribe` takes over the connection, streaming events until the
+        // client disconnects, so it's handled separately and ends the loop.
+        if matches!(command, GatewayCommand::Subscribe) {
+            stream_events(&mut writer, &state);
+            break;
+        }
+
+        if write_response(&mut writer, &handle_command(command, &state)).is_err() {
+            break;
+        }
+    }
+}
+
+fn handle_command(command: GatewayCommand, state: &SharedState) -> GatewayResponse {
+    match command {
+        GatewayCommand::List => match lock_state(state) {
+            Ok(guard) => GatewayResponse::Services {
+                services: guard
+                    .list()
+                    .into_iter()
+                    .map(|svc| ServiceRecord {
+                        name: svc.name.clone(),
+                        version: svc.version_req.to_string(),
+                    })
+                    .collect(),
+            },
+            Err(err) => error_response(err),
+        },
+        GatewayCommand::Set { name, version } => match VersionReq::parse(&version) {
+            Ok(version_req) => match lock_state(state) {
+                Ok(mut guard) => match guard.set_service(name.clone(), version_req.clone()) {
+                    Ok(()) => GatewayResponse::Services {
+                        services: vec![ServiceRecord {
+                            name,
+                            version: version_req.to_string(),
+                        }],
+                    },
+                    Err(err) => GatewayResponse::Error {
+                        message: format!("internal error: {err}"),
+                    },
+                },
+                Err(err) => error_response(err),
+            },
+            Err(err) => GatewayResponse::Error {
+                message: format!("invalid version requirement '{version}': {err}"),
+            },
+        },
+        GatewayCommand::Remove { name } => match lock_state(state) {
+            Ok(mut guard) => match guard.remove_service(&name) {
+                Ok(removed) => GatewayResponse::Removed { removed },
+                Err(err) => GatewayResponse::Error {
+                    message: format!("internal error: {err}"),
+                },
+            },
+            Err(err) => error_response(err),
+        },
+        GatewayCommand::Subscribe => unreachable!("Subscribe is handled by stream_events"),
+    }
+}
+
+fn stream_events(writer: &mut UnixStream, state: &SharedState) {
+    let receiver = {
+        let mut guard = match lock_state(state) {
+            Ok(guard) => guard,
+            Err(err) => {
+                let _ = write_response(writer, &error_response(err));
+                return;
+            }
+        };
+        guard.subscribe_with_current_state()
+    };
+
+    while let Ok(event) = receiver.recv() {
+        if write_response(writer, &GatewayResponse::Event { event }).is_err() {
+            break;
+        }
+    }
+}
+
+fn write_response(writer: &mut UnixStream, response: &GatewayResponse) -> io::Result<()> {
+    let mut line = serde_json::to_string(response)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())
+}
+
+fn error_response(err: io::Error) -> GatewayResponse {
+    GatewayResponse::Error {
+        message: format!("state lock poisoned: {err}"),
+    }
+}
+
+fn lock_state(state: &SharedState) -> io::Result<std::sync::MutexGuard<'_, DesiredState>> {
+    state
+        .lock()
+        .map_err(|_| io::Error::new(ErrorKind::Other, "state lock poisoned"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_each_command_variant() {
+        assert!(matches!(
+            serde_json::from_str::<GatewayCommand>(r#"{"command":"list"}"#).unwrap(),
+            GatewayCommand::List
+        ));
+        assert!(matches!(
+            serde_json::from_str::<GatewayCommand>(r#"{"command":"subscribe"}"#).unwrap(),
+            GatewayCommand::Subscribe
+        ));
+
+        match serde_json::from_str::<GatewayCommand>(
+            r#"{"command":"set","name":"api","version":"^1.2.3"}"#,
+        )
+        .unwrap()
+        {
+            GatewayCommand::Set { name, version } => {
+                assert_eq!(name, "api");
+                assert_eq!(version, "^1.2.3");
+            }
+            other => panic!("expected Set, got {other:?}"),
+        }
+
+        match serde_json::from_str::<GatewayCommand>(r#"{"command":"remove","name":"api"}"#)
+            .unwrap()
+        {
+            GatewayCommand::Remove { name } => assert_eq!(name, "api"),
+            other => panic!("expected Remove, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn serializes_responses_with_a_type_tag() {
+        let services = serde_json::to_value(&GatewayResponse::Services {
+            services: vec![ServiceRecord {
+                name: "api".to_string(),
+                version: "^1.2.3".to_string(),
+            }],
+        })
+        .unwrap();
+        assert_eq!(services["type"], "services");
+        assert_eq!(services["services"][0]["name"], "api");
+
+        let error = serde_json::to_value(&GatewayResponse::Error {
+            message: "boom".to_string(),
+        })
+        .unwrap();
+        assert_eq!(error["type"], "error");
+        assert_eq!(error["message"], "boom");
+    }
+}
@@ -1,6 +1,9 @@
+mod cli;
 mod desired_state_file;
 mod error;
+mod gateway;
 mod state;
+mod status;
 mod watcher;
 mod web_api;
 
@@ -14,11 +17,22 @@ use std::path::PathBuf;
 async fn main() -> AppResult<()> {
     env_logger::init();
 
+    // `--watch-only` runs the standalone CLI watcher (`cli::run`) instead of
+    // the Rocket server: same state file, but logging state changes to
+    // stdout rather than serving them over HTTP/the gateway socket.
+    if env::args().skip(1).any(|arg| arg == "--watch-only") {
+        return cli::run();
+    }
+
     let state_path = resolve_state_path()?;
+    let socket_path = resolve_socket_path()?;
     let desired_state = DesiredState::load(state_path)?;
     let shared_state: SharedState = std::sync::Arc::new(std::sync::Mutex::new(desired_state));
 
     watcher::spawn(shared_state.clone())?;
+    if let Some(socket_path) = socket_path {
+        gateway::spawn(shared_state.clone(), socket_path)?;
+    }
     web_api::launch(shared_state).await?;
 
     Ok(())
@@ -30,13 +44,59 @@ fn resolve_state_path() -> AppResult<PathBuf> {
 
     let mut args = env::args().skip(1);
     while let Some(arg) = args.next() {
-        if arg == "--file" {
-            let path = args
-                .next()
-                .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "--file requires a path"))?;
-            desired_file = path;
+        match arg.as_str() {
+            "--file" => {
+                let path = args.next().ok_or_else(|| {
+                    io::Error::new(ErrorKind::InvalidInput, "--file requires a path")
+                })?;
+                desired_file = path;
+            }
+            // Accepted here for parity with the standalone CLI watcher's
+            // `--format` flag. This binary always logs through
+            // `env_logger`, so the value just needs validating, not acting
+            // on.
+            "--format" => {
+                let value = args.next().ok_or_else(|| {
+                    io::Error::new(ErrorKind::InvalidInput, "--format requires 'text' or 'json'")
+                })?;
+                if value != "text" && value != "json" {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("invalid --format value '{value}', expected 'text' or 'json'"),
+                    )
+                    .into());
+                }
+            }
+            // Consumed here too so it isn't mistaken for an unrecognized
+            // positional argument; the actual value is read again in
+            // `resolve_socket_path`.
+            "--socket" => {
+                args.next().ok_or_else(|| {
+                    io::Error::new(ErrorKind::InvalidInput, "--socket requires a path")
+                })?;
+            }
+            _ => {}
         }
     }
 
     Ok(PathBuf::from(desired_file))
 }
+
+/// Resolves the optional Unix domain socket path for the [`gateway`]
+/// control channel, from `--socket` or `DESIRED_STATE_SOCKET`. Returns
+/// `None` when neither is set, in which case the gateway is not started.
+fn resolve_socket_path() -> AppResult<Option<PathBuf>> {
+    let mut socket_path = env::var("DESIRED_STATE_SOCKET").ok().map(PathBuf::from);
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--socket" {
+            let path = args.next().ok_or_else(|| {
+                io::Error::new(ErrorKind::InvalidInput, "--socket requires a path")
+            })?;
+            socket_path = Some(PathBuf::from(path));
+        }
+    }
+
+    Ok(socket_path)
+}
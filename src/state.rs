@@ -1,27 +1,47 @@
-use anyhow::{Context, Result};
+use crate::status::ProbeSpec;
+use anyhow::Result;
+use log::debug;
 use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::fs;
 use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+
+pub type SharedState = Arc<Mutex<DesiredState>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StateEvent {
+    StateUpdated {
+        version: Version,
+        services: Vec<Service>,
+    },
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Service {
     pub name: String,
     #[serde(rename = "version")]
     pub version_req: VersionReq,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe: Option<ProbeSpec>,
 }
 
 impl Service {
     pub fn new(name: String, version_req: VersionReq) -> Self {
-        Self { name, version_req }
+        Self {
+            name,
+            version_req,
+            probe: None,
+        }
     }
 
     pub fn placeholder(name: &str) -> Self {
         Self {
             name: name.to_string(),
             version_req: VersionReq::STAR.clone(),
+            probe: None,
         }
     }
 }
@@ -40,50 +60,34 @@ impl Hash for Service {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct DesiredStateFile {
-    #[serde(default = "get_current_file_version")]
-    version: Version,
-    #[serde(default)]
-    services: Vec<Service>,
-}
-
 pub struct DesiredState {
     path: PathBuf,
     file_version: Version,
     services: HashSet<Service>,
+    subscribers: Vec<mpsc::Sender<StateEvent>>,
 }
 
 impl DesiredState {
     pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
         let path = path.into();
+        let (file_version, services) = Self::read_from_path(&path)?;
 
-        let (file_version, services) = if path.exists() {
-            let raw = fs::read_to_string(&path)
-                .with_context(|| format!("failed to read desired state file {}", path.display()))?;
-            if raw.trim().is_empty() {
-                (get_current_file_version(), HashSet::new())
-            } else {
-                let parsed: DesiredStateFile = serde_yaml::from_str(&raw).with_context(|| {
-                    format!("failed to parse desired state file {}", path.display())
-                })?;
-                (parsed.version, parsed.services.into_iter().collect())
-            }
-        } else {
-            (get_current_file_version(), HashSet::new())
-        };
-
-        let state = Self {
+        Ok(Self {
             path,
             file_version,
             services,
-        };
+            subscribers: Vec::new(),
+        })
+    }
 
-        if !state.path.exists() {
-            state.persist()?;
-        }
+    fn read_from_path(path: &Path) -> Result<(Version, HashSet<Service>)> {
+        let (file_version, services) =
+            crate::desired_state_file::read(path).map_err(|err| anyhow::anyhow!("{err}"))?;
+        Ok((file_version, services.into_values().collect()))
+    }
 
-        Ok(state)
+    pub fn path(&self) -> PathBuf {
+        self.path.clone()
     }
 
     pub fn list(&self) -> Vec<&Service> {
@@ -93,9 +97,17 @@ impl DesiredState {
     }
 
     pub fn set_service(&mut self, name: String, version_req: VersionReq) -> Result<()> {
-        let new_service = Service::new(name, version_req);
+        let existing_probe = self
+            .services
+            .get(&Service::placeholder(&name))
+            .and_then(|svc| svc.probe.clone());
+
+        let mut new_service = Service::new(name, version_req);
+        new_service.probe = existing_probe;
         self.services.replace(new_service);
-        self.persist()
+        self.persist()?;
+        self.emit_current_state();
+        Ok(())
     }
 
     pub fn remove_service(&mut self, name: &str) -> Result<bool> {
@@ -103,35 +115,210 @@ impl DesiredState {
         let existed = self.services.take(&placeholder).is_some();
         if existed {
             self.persist()?;
+            self.emit_current_state();
         }
         Ok(existed)
     }
 
-    fn persist(&self) -> Result<()> {
-        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("failed to create directory {}", parent.display()))?;
+    pub fn reload_from_disk(&mut self) -> Result<()> {
+        let (file_version, services) = Self::read_from_path(&self.path)?;
+        if file_version == self.file_version && services_match(&services, &self.services) {
+            // The watcher fires on our own persist() rename as well as on
+            // external edits; skip the broadcast when nothing actually
+            // changed so subscribers don't see every mutation twice.
+            debug!("Reloaded desired state is unchanged from memory; not re-broadcasting");
+            return Ok(());
         }
+        self.file_version = file_version;
+        self.services = services;
+        self.emit_current_state();
+        Ok(())
+    }
 
-        let mut services: Vec<_> = self.services.iter().cloned().collect();
-        services.sort_by(|a, b| a.name.cmp(&b.name));
+    // Sends the new subscriber the current state without touching anyone
+    // else's channel, so it doesn't also re-notify subscribers who are
+    // already connected.
+    pub fn subscribe_with_current_state(&mut self) -> mpsc::Receiver<StateEvent> {
+        let (tx, rx) = mpsc::channel();
+        let _ = tx.send(self.current_state_event());
+        self.subscribers.push(tx);
+        rx
+    }
+
+    pub fn emit_current_state(&mut self) {
+        let event = self.current_state_event();
+        self.broadcast(event);
+    }
 
-        let yaml = serde_yaml::to_string(&DesiredStateFile {
+    fn current_state_event(&self) -> StateEvent {
+        StateEvent::StateUpdated {
             version: self.file_version.clone(),
-            services,
-        })
-        .with_context(|| {
-            format!(
-                "failed to serialize desired state to YAML for {}",
-                self.path.display()
-            )
-        })?;
+            services: self.services.iter().cloned().collect(),
+        }
+    }
 
-        fs::write(&self.path, yaml)
-            .with_context(|| format!("failed to write desired state file {}", self.path.display()))
+    fn broadcast(&mut self, event: StateEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
     }
+
+    fn persist(&self) -> Result<()> {
+        let services = self
+            .services
+            .iter()
+            .map(|svc| (svc.name.clone(), svc.clone()))
+            .collect();
+
+        crate::desired_state_file::write(&self.path, &self.file_version, &services)
+            .map_err(|err| anyhow::anyhow!("{err}"))
+    }
+}
+
+// `Service`'s own `PartialEq`/`Hash` key on `name` alone (so
+// `HashSet::replace`/`take` work as a by-name map); this checks full
+// content equality for each matched-by-name pair instead.
+fn services_match(a: &HashSet<Service>, b: &HashSet<Service>) -> bool {
+    a.len() == b.len()
+        && a.iter().all(|svc| {
+            b.get(svc)
+                .is_some_and(|other| svc.version_req == other.version_req && svc.probe == other.probe)
+        })
+}
+
+pub fn temp_path_for(path: &Path) -> PathBuf {
+    let mut temp_name = path.as_os_str().to_os_string();
+    temp_name.push(".tmp");
+    PathBuf::from(temp_name)
 }
 
-fn get_current_file_version() -> Version {
-    Version::new(0,1,0)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    #[test]
+    fn state_updated_event_serializes_with_a_type_tag() {
+        let event = StateEvent::StateUpdated {
+            version: Version::new(0, 1, 0),
+            services: vec![Service::new(
+                "api".to_string(),
+                VersionReq::parse("^1.2.3").unwrap(),
+            )],
+        };
+
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["type"], "state_updated");
+        assert_eq!(value["version"], "0.1.0");
+        assert_eq!(value["services"][0]["name"], "api");
+        assert_eq!(value["services"][0]["version"], "^1.2.3");
+    }
+
+    #[test]
+    fn set_service_preserves_an_existing_probe() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("desired_state_test_{}.yml", std::process::id()));
+
+        let mut state = DesiredState {
+            path: path.clone(),
+            file_version: Version::new(0, 1, 0),
+            services: HashSet::new(),
+            subscribers: Vec::new(),
+        };
+
+        state
+            .set_service("api".to_string(), VersionReq::parse("^1.0.0").unwrap())
+            .unwrap();
+        state.services.replace({
+            let mut svc = Service::new("api".to_string(), VersionReq::parse("^1.0.0").unwrap());
+            svc.probe = Some(ProbeSpec {
+                kind: crate::status::ProbeKind::Tcp,
+                target: "localhost:8080".to_string(),
+            });
+            svc
+        });
+
+        state
+            .set_service("api".to_string(), VersionReq::parse("^2.0.0").unwrap())
+            .unwrap();
+
+        let stored = state.services.get(&Service::placeholder("api")).unwrap();
+        assert_eq!(stored.version_req, VersionReq::parse("^2.0.0").unwrap());
+        assert!(stored.probe.is_some());
+
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(temp_path_for(&path));
+    }
+
+    #[test]
+    fn reload_from_disk_does_not_rebroadcast_when_the_file_matches_memory() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "desired_state_test_noop_reload_{}.yml",
+            std::process::id()
+        ));
+
+        let mut services = HashSet::new();
+        services.replace(Service::new(
+            "api".to_string(),
+            VersionReq::parse("^1.0.0").unwrap(),
+        ));
+
+        let mut state = DesiredState {
+            path: path.clone(),
+            file_version: Version::new(0, 1, 0),
+            services,
+            subscribers: Vec::new(),
+        };
+        state.persist().unwrap();
+
+        let events = state.subscribe_with_current_state();
+        let _ = events.try_recv().unwrap();
+
+        state.reload_from_disk().unwrap();
+
+        assert!(events.try_recv().is_err());
+
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(temp_path_for(&path));
+    }
+
+    #[test]
+    fn reload_from_disk_rebroadcasts_when_the_file_actually_changed() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "desired_state_test_real_reload_{}.yml",
+            std::process::id()
+        ));
+
+        let mut services = HashSet::new();
+        services.replace(Service::new(
+            "api".to_string(),
+            VersionReq::parse("^1.0.0").unwrap(),
+        ));
+
+        let mut state = DesiredState {
+            path: path.clone(),
+            file_version: Version::new(0, 1, 0),
+            services,
+            subscribers: Vec::new(),
+        };
+        state.persist().unwrap();
+
+        let events = state.subscribe_with_current_state();
+        let _ = events.try_recv().unwrap();
+
+        let mut external_services = BTreeMap::new();
+        let external_service = Service::new("api".to_string(), VersionReq::parse("^2.0.0").unwrap());
+        external_services.insert(external_service.name.clone(), external_service);
+        crate::desired_state_file::write(&path, &Version::new(0, 1, 0), &external_services)
+            .unwrap();
+
+        state.reload_from_disk().unwrap();
+
+        assert!(events.try_recv().is_ok());
+
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(temp_path_for(&path));
+    }
 }
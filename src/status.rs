@@ -0,0 +1,309 @@
+use crate::state::Service;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::net::TcpStream;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeState {
+    Up,
+    Down,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeResult {
+    pub status: ProbeState,
+    pub observed_version: Option<Version>,
+}
+
+impl ProbeResult {
+    pub fn unknown() -> Self {
+        Self {
+            status: ProbeState::Unknown,
+            observed_version: None,
+        }
+    }
+
+    fn down() -> Self {
+        Self {
+            status: ProbeState::Down,
+            observed_version: None,
+        }
+    }
+
+    fn up(version: Version) -> Self {
+        Self {
+            status: ProbeState::Up,
+            observed_version: Some(version),
+        }
+    }
+}
+
+pub trait Probe: Send {
+    fn check(&self, svc: &Service) -> ProbeResult;
+}
+
+// `target` is interpreted according to `kind`: a URL, a `host:port` pair,
+// or a shell command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProbeSpec {
+    pub kind: ProbeKind,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeKind {
+    Http,
+    Tcp,
+    Command,
+}
+
+impl ProbeSpec {
+    pub fn build(&self) -> Box<dyn Probe> {
+        match self.kind {
+            ProbeKind::Http => Box::new(HttpProbe {
+                url: self.target.clone(),
+            }),
+            ProbeKind::Tcp => Box::new(TcpProbe {
+                addr: self.target.clone(),
+            }),
+            ProbeKind::Command => Box::new(CommandProbe {
+                command: self.target.clone(),
+            }),
+        }
+    }
+}
+
+struct HttpProbe {
+    url: String,
+}
+
+impl Probe for HttpProbe {
+    fn check(&self, _svc: &Service) -> ProbeResult {
+        let agent = ureq::AgentBuilder::new().timeout(PROBE_TIMEOUT).build();
+        match agent.get(&self.url).call() {
+            Ok(response) => {
+                let mut body = String::new();
+                if response.into_reader().read_to_string(&mut body).is_err() {
+                    return ProbeResult::unknown();
+                }
+                match Version::parse(body.trim()) {
+                    Ok(version) => ProbeResult::up(version),
+                    Err(_) => ProbeResult::unknown(),
+                }
+            }
+            Err(ureq::Error::Transport(transport))
+                if transport.kind() == ureq::ErrorKind::ConnectionFailed =>
+            {
+                ProbeResult::down()
+            }
+            Err(_) => ProbeResult::unknown(),
+        }
+    }
+}
+
+struct TcpProbe {
+    addr: String,
+}
+
+impl Probe for TcpProbe {
+    fn check(&self, _svc: &Service) -> ProbeResult {
+        let addrs = match self.addr.to_socket_addrs_or_unknown() {
+            Some(addrs) => addrs,
+            None => return ProbeResult::unknown(),
+        };
+
+        for addr in addrs {
+            if TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok() {
+                return ProbeResult {
+                    status: ProbeState::Up,
+                    observed_version: None,
+                };
+            }
+        }
+        ProbeResult::down()
+    }
+}
+
+struct CommandProbe {
+    command: String,
+}
+
+impl CommandProbe {
+    // Takes an injectable timeout so tests can exercise the timeout path
+    // without waiting out the real PROBE_TIMEOUT.
+    fn check_with_timeout(&self, timeout: Duration) -> ProbeResult {
+        let mut child = match Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(_) => return ProbeResult::unknown(),
+        };
+
+        // A child that writes more than a pipe buffer's worth of stdout
+        // before exiting will block on write() until someone reads it, so
+        // stdout has to be drained concurrently with waiting for exit --
+        // not after, or try_wait() would never see it finish.
+        let mut pipe = match child.stdout.take() {
+            Some(pipe) => pipe,
+            None => return ProbeResult::unknown(),
+        };
+        let reader = thread::spawn(move || {
+            let mut stdout = String::new();
+            let _ = pipe.read_to_string(&mut stdout);
+            stdout
+        });
+
+        // Unlike HttpProbe/TcpProbe, a shell command has no built-in
+        // timeout, so poll for exit ourselves and kill it if it outlives
+        // the timeout, rather than blocking on `output()` indefinitely.
+        let deadline = Instant::now() + timeout;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) => {
+                    if Instant::now() >= deadline {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        return ProbeResult::unknown();
+                    }
+                    thread::sleep(COMMAND_POLL_INTERVAL);
+                }
+                Err(_) => return ProbeResult::unknown(),
+            }
+        };
+
+        let stdout = reader.join().unwrap_or_default();
+
+        if !status.success() {
+            return ProbeResult::down();
+        }
+
+        match Version::parse(stdout.trim()) {
+            Ok(version) => ProbeResult::up(version),
+            Err(_) => ProbeResult::unknown(),
+        }
+    }
+}
+
+impl Probe for CommandProbe {
+    fn check(&self, _svc: &Service) -> ProbeResult {
+        self.check_with_timeout(PROBE_TIMEOUT)
+    }
+}
+
+trait ToSocketAddrsOrUnknown {
+    fn to_socket_addrs_or_unknown(&self) -> Option<Vec<std::net::SocketAddr>>;
+}
+
+impl ToSocketAddrsOrUnknown for str {
+    fn to_socket_addrs_or_unknown(&self) -> Option<Vec<std::net::SocketAddr>> {
+        use std::net::ToSocketAddrs;
+        self.to_socket_addrs().ok().map(|addrs| addrs.collect())
+    }
+}
+
+// Runs each service's probe on its own thread, collecting results over a
+// channel until `timeout` elapses; threads that haven't reported by the
+// deadline are left to finish on their own. Services with no probe
+// configured, or whose probe didn't finish in time, are simply absent
+// from the result map.
+pub fn probe_all(services: Vec<Service>, timeout: Duration) -> std::collections::HashMap<String, ProbeResult> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut expected = 0;
+
+    for svc in services {
+        let Some(spec) = svc.probe.clone() else {
+            continue;
+        };
+        expected += 1;
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            let probe = spec.build();
+            let result = probe.check(&svc);
+            let _ = tx.send((svc.name.clone(), result));
+        });
+    }
+    drop(tx);
+
+    let deadline = Instant::now() + timeout;
+    let mut results = std::collections::HashMap::new();
+    while results.len() < expected {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        match rx.recv_timeout(remaining) {
+            Ok((name, result)) => {
+                results.insert(name, result);
+            }
+            Err(_) => break,
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_spec_deserializes_kind_and_target_from_yaml() {
+        let spec: ProbeSpec =
+            serde_yaml::from_str("kind: command\ntarget: \"echo 1.0.0\"").unwrap();
+        assert_eq!(spec.kind, ProbeKind::Command);
+        assert_eq!(spec.target, "echo 1.0.0");
+    }
+
+    #[test]
+    fn command_probe_reports_the_version_it_prints() {
+        let probe = ProbeKind::Command.pipe_target("echo 1.2.3");
+        let result = probe.check(&Service::placeholder("whatever"));
+        assert_eq!(result.status, ProbeState::Up);
+        assert_eq!(result.observed_version, Some(Version::new(1, 2, 3)));
+    }
+
+    #[test]
+    fn command_probe_reports_down_on_nonzero_exit() {
+        let probe = ProbeKind::Command.pipe_target("exit 1");
+        let result = probe.check(&Service::placeholder("whatever"));
+        assert_eq!(result.status, ProbeState::Down);
+    }
+
+    #[test]
+    fn command_probe_times_out_on_a_hanging_command() {
+        let probe = CommandProbe {
+            command: "sleep 60".to_string(),
+        };
+        let result = probe.check_with_timeout(Duration::from_millis(50));
+        assert_eq!(result.status, ProbeState::Unknown);
+    }
+
+    trait PipeTarget {
+        fn pipe_target(self, target: &str) -> Box<dyn Probe>;
+    }
+
+    impl PipeTarget for ProbeKind {
+        fn pipe_target(self, target: &str) -> Box<dyn Probe> {
+            ProbeSpec {
+                kind: self,
+                target: target.to_string(),
+            }
+            .build()
+        }
+    }
+}
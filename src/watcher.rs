@@ -1,6 +1,6 @@
 use crate::{
     error::AppResult,
-    state::{DesiredState, SharedState, StateEvent},
+    state::{DesiredState, SharedState, StateEvent, temp_path_for},
 };
 use log::{debug, info, warn};
 use notify::{Event, EventKind, RecursiveMode, Watcher};
@@ -27,14 +27,9 @@ pub fn spawn(state: SharedState) -> AppResult<()> {
 
 fn watch_loop(state: SharedState) -> AppResult<()> {
     let events = {
-        let guard = lock_state(&state)?;
-        guard.subscribe()
-    };
-
-    {
         let mut guard = lock_state(&state)?;
-        guard.emit_current_state();
-    }
+        guard.subscribe_with_current_state()
+    };
 
     let file_path = {
         let guard = lock_state(&state)?;
@@ -42,13 +37,19 @@ fn watch_loop(state: SharedState) -> AppResult<()> {
     };
 
     let watch_target = canonicalize_for_watch(&file_path);
+    let watch_dir = watch_directory(&watch_target);
 
     let (watch_tx, watch_rx) = mpsc::channel();
     let mut watcher = notify::recommended_watcher(move |res| {
         let _ = watch_tx.send(res);
     })?;
 
-    watcher.watch(&watch_target, RecursiveMode::NonRecursive)?;
+    // Watch the parent directory rather than the file itself: `persist()`
+    // replaces the file via `rename`, which on Linux severs inotify's watch
+    // on the old inode (IN_DELETE_SELF/IN_IGNORED) after the very first
+    // write, silently killing any watch placed directly on the path.
+    // Watching the directory and filtering by filename survives renames.
+    watcher.watch(&watch_dir, RecursiveMode::NonRecursive)?;
 
     info!("Watching desired state file '{}'", watch_target.display());
 
@@ -97,14 +98,25 @@ fn canonicalize_for_watch(path: &Path) -> PathBuf {
     fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
 }
 
+fn watch_directory(target: &Path) -> PathBuf {
+    target
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf()
+}
+
 fn event_affects_target(event: &Event, target: &Path) -> bool {
     if event.paths.is_empty() {
         return true;
     }
-    event
-        .paths
-        .iter()
-        .any(|path| canonicalize_for_watch(path) == target)
+    let temp_target = temp_path_for(target);
+    event.paths.iter().any(|path| {
+        if path == &temp_target {
+            return false;
+        }
+        canonicalize_for_watch(path) == target
+    })
 }
 
 fn is_state_change(kind: &EventKind) -> bool {
@@ -147,3 +159,48 @@ fn lock_state<'a>(state: &'a SharedState) -> AppResult<std::sync::MutexGuard<'a,
         .lock()
         .map_err(|_| io::Error::new(ErrorKind::Other, "state lock poisoned"))?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, ModifyKind};
+
+    fn event_for(paths: Vec<PathBuf>) -> Event {
+        let mut event = Event::new(EventKind::Modify(ModifyKind::Any));
+        event.paths = paths;
+        event
+    }
+
+    #[test]
+    fn ignores_events_for_the_temp_file() {
+        let target = PathBuf::from("/tmp/desired_state.yml");
+        let event = event_for(vec![temp_path_for(&target)]);
+        assert!(!event_affects_target(&event, &target));
+    }
+
+    #[test]
+    fn matches_events_for_the_target_file() {
+        let target = PathBuf::from("/tmp/desired_state.yml");
+        let event = event_for(vec![target.clone()]);
+        assert!(event_affects_target(&event, &target));
+    }
+
+    #[test]
+    fn matches_events_with_no_paths() {
+        let target = PathBuf::from("/tmp/desired_state.yml");
+        let event = Event::new(EventKind::Create(CreateKind::Any));
+        assert!(event_affects_target(&event, &target));
+    }
+
+    #[test]
+    fn watch_directory_is_the_parent_of_the_target() {
+        let target = PathBuf::from("/tmp/state/desired_state.yml");
+        assert_eq!(watch_directory(&target), PathBuf::from("/tmp/state"));
+    }
+
+    #[test]
+    fn watch_directory_falls_back_to_cwd_for_a_bare_filename() {
+        let target = PathBuf::from("desired_state.yml");
+        assert_eq!(watch_directory(&target), PathBuf::from("."));
+    }
+}
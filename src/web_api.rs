@@ -1,13 +1,33 @@
 use crate::{
     error::AppResult,
-    state::{DesiredState, Service, SharedState},
+    state::{DesiredState, Service, SharedState, StateEvent},
+    status::{self, ProbeState},
 };
 use rocket::http::Status;
-use rocket::response::status;
+use rocket::response::status as http_status;
+use rocket::response::stream::{Event, EventStream};
 use rocket::serde::{Deserialize, Serialize, json::Json};
-use rocket::{Build, Rocket, State, delete, get, put, routes};
+use rocket::{Build, Rocket, Shutdown, State, delete, get, put, routes};
 use semver::VersionReq;
-use std::sync::MutexGuard;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+const STATUS_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const DISCONNECT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// Dropped when the EventStream! generator in stream_events ends (client
+// disconnect or server shutdown), signalling the pump thread to stop.
+struct DisconnectGuard(Arc<AtomicBool>);
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
 
 #[derive(Debug, Serialize)]
 struct ServiceResponse {
@@ -29,10 +49,19 @@ struct SetServiceRequest {
     version: String,
 }
 
+#[derive(Debug, Serialize)]
+struct ServiceStatusResponse {
+    name: String,
+    desired: String,
+    observed: Option<String>,
+    status: ProbeState,
+    satisfies_requirement: Option<bool>,
+}
+
 #[get("/services")]
 fn list_services(
     state: &State<SharedState>,
-) -> Result<Json<Vec<ServiceResponse>>, status::Custom<String>> {
+) -> Result<Json<Vec<ServiceResponse>>, http_status::Custom<String>> {
     let guard = lock_state(state)?;
     let services = guard
         .list()
@@ -47,9 +76,9 @@ fn upsert_service(
     state: &State<SharedState>,
     name: String,
     payload: Json<SetServiceRequest>,
-) -> Result<Json<ServiceResponse>, status::Custom<String>> {
+) -> Result<Json<ServiceResponse>, http_status::Custom<String>> {
     let version_req = VersionReq::parse(&payload.version).map_err(|err| {
-        status::Custom(
+        http_status::Custom(
             Status::BadRequest,
             format!("invalid version requirement '{}': {}", payload.version, err),
         )
@@ -70,17 +99,105 @@ fn upsert_service(
 fn delete_service(
     state: &State<SharedState>,
     name: String,
-) -> Result<Status, status::Custom<String>> {
+) -> Result<Status, http_status::Custom<String>> {
     let mut guard = lock_state(state)?;
     match guard.remove_service(&name).map_err(internal_error)? {
         true => Ok(Status::NoContent),
-        false => Err(status::Custom(
+        false => Err(http_status::Custom(
             Status::NotFound,
             format!("service '{}' not found", name),
         )),
     }
 }
 
+#[get("/status")]
+fn service_status(
+    state: &State<SharedState>,
+) -> Result<Json<Vec<ServiceStatusResponse>>, http_status::Custom<String>> {
+    let services: Vec<Service> = {
+        let guard = lock_state(state)?;
+        guard.list().into_iter().cloned().collect()
+    };
+
+    let mut results = status::probe_all(services.clone(), STATUS_PROBE_TIMEOUT);
+
+    let responses = services
+        .into_iter()
+        .map(|svc| {
+            let result = results
+                .remove(&svc.name)
+                .unwrap_or_else(status::ProbeResult::unknown);
+            let satisfies_requirement = result
+                .observed_version
+                .as_ref()
+                .map(|version| svc.version_req.matches(version));
+
+            ServiceStatusResponse {
+                name: svc.name,
+                desired: svc.version_req.to_string(),
+                observed: result.observed_version.map(|version| version.to_string()),
+                status: result.status,
+                satisfies_requirement,
+            }
+        })
+        .collect();
+
+    Ok(Json(responses))
+}
+
+#[get("/events")]
+fn stream_events(
+    state: &State<SharedState>,
+    mut shutdown: Shutdown,
+) -> Result<EventStream![Event + '_], http_status::Custom<String>> {
+    let receiver = {
+        let mut guard = lock_state(state)?;
+        guard.subscribe_with_current_state()
+    };
+
+    let disconnected = Arc::new(AtomicBool::new(false));
+    let pump_disconnected = disconnected.clone();
+    let (tx, mut rx) = rocket::tokio::sync::mpsc::unbounded_channel::<StateEvent>();
+    // The subscriber is a blocking mpsc::Receiver, so pump it into an
+    // unbounded async channel the event stream can .await on.
+    thread::spawn(move || {
+        while !pump_disconnected.load(Ordering::SeqCst) {
+            match receiver.recv_timeout(DISCONNECT_POLL_INTERVAL) {
+                Ok(event) => {
+                    if tx.send(event).is_err() {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+
+    Ok(EventStream! {
+        // Dropped when this generator ends for any reason, including the
+        // client disconnecting, so the pump thread above notices within
+        // DISCONNECT_POLL_INTERVAL instead of only on the next broadcast.
+        let _guard = DisconnectGuard(disconnected);
+
+        loop {
+            let event = rocket::tokio::select! {
+                event = rx.recv() => event,
+                _ = &mut shutdown => break,
+            };
+
+            match event {
+                Some(event) => {
+                    let payload = rocket::serde::json::to_string(&event)
+                        .unwrap_or_else(|_| "{}".to_string());
+                    yield Event::data(payload);
+                }
+                None => break,
+            }
+        }
+    })
+}
+
 pub async fn launch(state: SharedState) -> AppResult<()> {
     build_rocket(state)
         .launch()
@@ -90,22 +207,29 @@ pub async fn launch(state: SharedState) -> AppResult<()> {
 }
 
 fn build_rocket(state: SharedState) -> Rocket<Build> {
-    rocket::build()
-        .manage(state)
-        .mount("/", routes![list_services, upsert_service, delete_service])
+    rocket::build().manage(state).mount(
+        "/",
+        routes![
+            list_services,
+            upsert_service,
+            delete_service,
+            service_status,
+            stream_events
+        ],
+    )
 }
 
 fn lock_state<'a>(
     state: &'a State<SharedState>,
-) -> Result<MutexGuard<'a, DesiredState>, status::Custom<String>> {
+) -> Result<MutexGuard<'a, DesiredState>, http_status::Custom<String>> {
     state
         .inner()
         .lock()
-        .map_err(|_| status::Custom(Status::InternalServerError, "state lock poisoned".into()))
+        .map_err(|_| http_status::Custom(Status::InternalServerError, "state lock poisoned".into()))
 }
 
-fn internal_error(err: Box<dyn std::error::Error + Send + Sync>) -> status::Custom<String> {
-    status::Custom(
+fn internal_error(err: Box<dyn std::error::Error + Send + Sync>) -> http_status::Custom<String> {
+    http_status::Custom(
         Status::InternalServerError,
         format!("internal error: {}", err),
     )